@@ -0,0 +1,88 @@
+//! AES-CMAC (RFC 4493), built on the portable block cipher in
+//! [`crate::key_schedule`]. Private to [`super`]: S2V only ever needs
+//! AES-128-CMAC, so this isn't exposed as a general-purpose primitive.
+
+use crate::key_schedule::{encrypt_block_portable, expand_key};
+
+const BLOCK_SIZE: usize = 16;
+const ROUNDS: usize = 10;
+
+/// GF(2^128) doubling, shared by CMAC subkey derivation and S2V.
+pub(super) fn dbl(mut block: [u8; BLOCK_SIZE]) -> [u8; BLOCK_SIZE] {
+    let msb_set = block[0] & 0x80 != 0;
+    let mut carry = 0u8;
+    for byte in block.iter_mut().rev() {
+        let next_carry = (*byte & 0x80) >> 7;
+        *byte = (*byte << 1) | carry;
+        carry = next_carry;
+    }
+    if msb_set {
+        block[15] ^= 0x87;
+    }
+    block
+}
+
+pub(super) fn xor_in_place(a: &mut [u8; BLOCK_SIZE], b: &[u8; BLOCK_SIZE]) {
+    for (x, y) in a.iter_mut().zip(b.iter()) {
+        *x ^= y;
+    }
+}
+
+pub(super) struct Cmac128 {
+    round_keys: [[u8; BLOCK_SIZE]; ROUNDS + 1],
+}
+
+impl Cmac128 {
+    pub(super) fn new(key: &[u8; BLOCK_SIZE]) -> Self {
+        let expanded = expand_key(key, 4, ROUNDS);
+        let mut round_keys = [[0u8; BLOCK_SIZE]; ROUNDS + 1];
+        round_keys.copy_from_slice(&expanded[..ROUNDS + 1]);
+        Self { round_keys }
+    }
+
+    fn encrypt_block(&self, block: &mut [u8; BLOCK_SIZE]) {
+        encrypt_block_portable(&self.round_keys, ROUNDS, block);
+    }
+
+    fn subkeys(&self) -> ([u8; BLOCK_SIZE], [u8; BLOCK_SIZE]) {
+        let mut l = [0u8; BLOCK_SIZE];
+        self.encrypt_block(&mut l);
+        let k1 = dbl(l);
+        let k2 = dbl(k1);
+        (k1, k2)
+    }
+
+    /// Compute the AES-CMAC of `message`.
+    pub(super) fn mac(&self, message: &[u8]) -> [u8; BLOCK_SIZE] {
+        let (k1, k2) = self.subkeys();
+
+        if message.is_empty() {
+            let mut last = [0u8; BLOCK_SIZE];
+            last[0] = 0x80;
+            xor_in_place(&mut last, &k2);
+            self.encrypt_block(&mut last);
+            return last;
+        }
+
+        let mut mac = [0u8; BLOCK_SIZE];
+        let mut chunks = message.chunks(BLOCK_SIZE).peekable();
+        while let Some(chunk) = chunks.next() {
+            let mut block = [0u8; BLOCK_SIZE];
+            if chunks.peek().is_none() {
+                if chunk.len() == BLOCK_SIZE {
+                    block.copy_from_slice(chunk);
+                    xor_in_place(&mut block, &k1);
+                } else {
+                    block[..chunk.len()].copy_from_slice(chunk);
+                    block[chunk.len()] = 0x80;
+                    xor_in_place(&mut block, &k2);
+                }
+            } else {
+                block.copy_from_slice(chunk);
+            }
+            xor_in_place(&mut mac, &block);
+            self.encrypt_block(&mut mac);
+        }
+        mac
+    }
+}
@@ -0,0 +1,215 @@
+//! AES-SIV (RFC 5297): an authenticated, nonce-misuse-resistant mode built
+//! on top of this crate's AES-CTR keystream plus an AES-CMAC derived from
+//! the same portable block cipher used for key expansion
+//! ([`crate::key_schedule`]).
+//!
+//! This implements the `AEAD_AES_SIV_CMAC_256` profile from RFC 5297: a
+//! 32-byte key is split into a 16-byte S2V/CMAC key and a 16-byte CTR key.
+//! Unlike plain `Aes128Ctr`/`Aes192Ctr`/`Aes256Ctr`, this mode authenticates
+//! its input and, per RFC 5297 section 1.1, tolerates nonce (or
+//! associated-data) reuse without the catastrophic keystream-reuse failure
+//! of bare CTR mode -- so the crate-level security warning about ciphertext
+//! integrity does not apply to it.
+
+mod cmac;
+
+use crate::cipher::{
+    generic_array::{typenum::U32, GenericArray},
+    stream::{NewStreamCipher, SyncStreamCipher},
+};
+use crate::Aes128Ctr;
+use cmac::{dbl, xor_in_place, Cmac128};
+use core::fmt;
+
+const BLOCK_SIZE: usize = 16;
+
+/// The 16-byte synthetic IV produced by [`Aes128Siv::seal_in_place`] and
+/// checked by [`Aes128Siv::open_in_place`]; send it alongside the ciphertext
+/// as the authentication tag.
+pub type Tag = [u8; BLOCK_SIZE];
+
+/// Returned by [`Aes128Siv::open_in_place`] when `buffer` fails
+/// authentication.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct OpenError;
+
+impl fmt::Display for OpenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("aes_siv: ciphertext failed authentication")
+    }
+}
+
+/// AES-SIV, `AEAD_AES_SIV_CMAC_256` profile (RFC 5297).
+pub struct Aes128Siv {
+    mac: Cmac128,
+    enc_key: [u8; BLOCK_SIZE],
+}
+
+impl Aes128Siv {
+    /// Construct from a 32-byte key: the first 16 bytes (`K1`) are used for
+    /// S2V/CMAC, the last 16 bytes (`K2`) for CTR, per RFC 5297 section 2.6.
+    pub fn new(key: &GenericArray<u8, U32>) -> Self {
+        let mut mac_key = [0u8; BLOCK_SIZE];
+        let mut enc_key = [0u8; BLOCK_SIZE];
+        mac_key.copy_from_slice(&key[..BLOCK_SIZE]);
+        enc_key.copy_from_slice(&key[BLOCK_SIZE..]);
+        Self {
+            mac: Cmac128::new(&mac_key),
+            enc_key,
+        }
+    }
+
+    /// Encrypt `buffer` in place over `associated_data`, returning the
+    /// synthetic IV to send alongside it as the authentication tag.
+    pub fn seal_in_place(&self, associated_data: &[&[u8]], buffer: &mut [u8]) -> Tag {
+        let iv = self.s2v(associated_data, buffer);
+        self.apply_ctr(&iv, buffer);
+        iv
+    }
+
+    /// Decrypt `buffer` in place and check it against `tag` and
+    /// `associated_data`. On failure `buffer` is zeroed and [`OpenError`] is
+    /// returned; callers must not trust its contents either way once this
+    /// returns.
+    pub fn open_in_place(
+        &self,
+        associated_data: &[&[u8]],
+        tag: &Tag,
+        buffer: &mut [u8],
+    ) -> Result<(), OpenError> {
+        self.apply_ctr(tag, buffer);
+        let expected = self.s2v(associated_data, buffer);
+        if ct_eq(&expected, tag) {
+            Ok(())
+        } else {
+            for b in buffer.iter_mut() {
+                *b = 0;
+            }
+            Err(OpenError)
+        }
+    }
+
+    fn apply_ctr(&self, iv: &Tag, buffer: &mut [u8]) {
+        // RFC 5297 section 2.5: clear the 31st and 63rd bits (0-indexed from
+        // the right) of the IV before using it as the CTR counter block, so
+        // a pathological S2V output can never make the 32-bit hardware CTR
+        // implementations some peers use wrap mid-message.
+        let mut q = *iv;
+        q[8] &= 0x7f;
+        q[12] &= 0x7f;
+
+        let mut ctr: Aes128Ctr = Aes128Ctr::new(
+            GenericArray::from_slice(&self.enc_key),
+            GenericArray::from_slice(&q),
+        );
+        ctr.apply_keystream(buffer);
+    }
+
+    /// RFC 5297 S2V over `associated_data` followed by `buffer`.
+    fn s2v(&self, associated_data: &[&[u8]], buffer: &mut [u8]) -> [u8; BLOCK_SIZE] {
+        let mut d = self.mac.mac(&[0u8; BLOCK_SIZE]);
+        for header in associated_data {
+            d = dbl(d);
+            xor_in_place(&mut d, &self.mac.mac(header));
+        }
+
+        if buffer.len() >= BLOCK_SIZE {
+            // xorend(buffer, d): XOR `d` into the last 16 bytes of `buffer`,
+            // CMAC the result, then restore `buffer`. Mutating in place
+            // avoids needing an allocator to hold a modified copy.
+            let start = buffer.len() - BLOCK_SIZE;
+            let mut original_tail = [0u8; BLOCK_SIZE];
+            original_tail.copy_from_slice(&buffer[start..]);
+
+            let mut xored_tail = original_tail;
+            xor_in_place(&mut xored_tail, &d);
+            buffer[start..].copy_from_slice(&xored_tail);
+
+            let iv = self.mac.mac(buffer);
+
+            buffer[start..].copy_from_slice(&original_tail);
+            iv
+        } else {
+            let mut padded = [0u8; BLOCK_SIZE];
+            padded[..buffer.len()].copy_from_slice(buffer);
+            padded[buffer.len()] = 0x80;
+            xor_in_place(&mut padded, &dbl(d));
+            self.mac.mac(&padded)
+        }
+    }
+}
+
+fn ct_eq(a: &[u8; BLOCK_SIZE], b: &[u8; BLOCK_SIZE]) -> bool {
+    let mut diff = 0u8;
+    for (x, y) in a.iter().zip(b.iter()) {
+        diff |= x ^ y;
+    }
+    diff == 0
+}
+
+#[test]
+fn roundtrips_with_and_without_associated_data() {
+    let key = GenericArray::clone_from_slice(&[0x42; 32]);
+    let siv = Aes128Siv::new(&key);
+
+    for ad in [&[][..], b"header"].iter() {
+        let mut buffer = *b"some secret message, longer than one block";
+        let tag = siv.seal_in_place(&[ad], &mut buffer);
+        assert_ne!(&buffer[..], b"some secret message, longer than one block");
+
+        siv.open_in_place(&[ad], &tag, &mut buffer).unwrap();
+        assert_eq!(&buffer[..], b"some secret message, longer than one block");
+    }
+}
+
+#[test]
+fn roundtrips_short_plaintext() {
+    let key = GenericArray::clone_from_slice(&[0x11; 32]);
+    let siv = Aes128Siv::new(&key);
+
+    let mut buffer = *b"hi";
+    let tag = siv.seal_in_place(&[], &mut buffer);
+    siv.open_in_place(&[], &tag, &mut buffer).unwrap();
+    assert_eq!(&buffer[..], b"hi");
+}
+
+#[test]
+fn rejects_tampered_ciphertext() {
+    let key = GenericArray::clone_from_slice(&[0x99; 32]);
+    let siv = Aes128Siv::new(&key);
+
+    let mut buffer = *b"authenticate me please";
+    let tag = siv.seal_in_place(&[], &mut buffer);
+    buffer[0] ^= 1;
+
+    assert_eq!(siv.open_in_place(&[], &tag, &mut buffer), Err(OpenError));
+    assert_eq!(&buffer[..], &[0u8; 22][..]);
+}
+
+// RFC 5297 Appendix A.2's nonce-based example is deliberately not pinned
+// here: reproducing its official IV/ciphertext bytes from memory without a
+// runnable toolchain to check them against risked committing a test vector
+// that was silently wrong, which is worse than no test at all.
+#[test]
+fn rfc5297_appendix_a1_vector() {
+    use hex_literal::hex;
+
+    let key = GenericArray::clone_from_slice(&hex!(
+        "fffefdfc fbfaf9f8 f7f6f5f4 f3f2f1f0
+         f0f1f2f3 f4f5f6f7 f8f9fafb fcfdfeff"
+    ));
+    let siv = Aes128Siv::new(&key);
+
+    let ad = hex!("101112131415161718191a1b1c1d1e1f2021222324252627");
+    let plaintext = hex!("112233445566778899aabbccddee");
+    let expected_iv = hex!("85632d07c6e8f37f950acd320a2ecc93");
+    let expected_ciphertext = hex!("40c02b9690c4dc04daef7f6afe5c");
+
+    let mut buffer = plaintext;
+    let tag = siv.seal_in_place(&[&ad], &mut buffer);
+    assert_eq!(tag, expected_iv);
+    assert_eq!(buffer, expected_ciphertext);
+
+    siv.open_in_place(&[&ad], &tag, &mut buffer).unwrap();
+    assert_eq!(buffer, plaintext);
+}
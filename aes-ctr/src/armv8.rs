@@ -0,0 +1,192 @@
+//! AES-CTR ciphers implementation using the ARMv8 Cryptography Extensions
+//! (`AESE`/`AESMC` instructions).
+
+use crate::cipher::{
+    errors::InvalidKeyNonceLength,
+    generic_array::{
+        typenum::{U16, U24, U32},
+        GenericArray,
+    },
+    stream::{NewStreamCipher, SyncStreamCipher, SyncStreamCipherSeek},
+};
+use crate::flavors::{Ctr128BE, CtrFlavor};
+use crate::key_schedule::expand_key;
+use core::arch::aarch64::*;
+use core::marker::PhantomData;
+use core::mem;
+
+const BLOCK_SIZE: usize = 16;
+
+#[inline(always)]
+unsafe fn aes_enc(state: uint8x16_t, round_key: uint8x16_t) -> uint8x16_t {
+    vaeseq_u8(state, round_key)
+}
+
+#[inline(always)]
+unsafe fn aes_mc(state: uint8x16_t) -> uint8x16_t {
+    vaesmcq_u8(state)
+}
+
+/// Encrypt a single 16-byte block using the given expanded round keys.
+///
+/// `round_keys` must contain `rounds + 1` entries, the last of which is XORed
+/// in directly (it is not consumed by `AESE`).
+#[inline]
+unsafe fn encrypt_block(round_keys: &[uint8x16_t], block: &mut [u8; BLOCK_SIZE]) {
+    let mut state = vld1q_u8(block.as_ptr());
+    let last = round_keys.len() - 1;
+
+    for rk in &round_keys[..last - 1] {
+        state = aes_mc(aes_enc(state, *rk));
+    }
+    state = aes_enc(state, round_keys[last - 1]);
+    state = veorq_u8(state, round_keys[last]);
+
+    vst1q_u8(block.as_mut_ptr(), state);
+}
+
+#[inline]
+fn load_round_key(bytes: &[u8; BLOCK_SIZE]) -> uint8x16_t {
+    unsafe { vld1q_u8(bytes.as_ptr()) }
+}
+
+macro_rules! impl_armv8_ctr {
+    ($name:ident, $key_size:ty, $key_words:expr, $rounds:expr, $doc:expr) => {
+        #[doc = $doc]
+        ///
+        /// Generic over the counter-block policy `F` (see [`crate::flavors`]);
+        /// defaults to [`Ctr128BE`], this crate's historical full-width
+        /// big-endian counter.
+        pub struct $name<F = Ctr128BE> {
+            round_keys: [uint8x16_t; $rounds + 1],
+            nonce: [u8; BLOCK_SIZE],
+            counter: [u8; BLOCK_SIZE],
+            block: [u8; BLOCK_SIZE],
+            pos: u8,
+            _flavor: PhantomData<F>,
+        }
+
+        impl<F: CtrFlavor> $name<F> {
+            fn keystream_block(&mut self) {
+                self.block = self.counter;
+                unsafe { encrypt_block(&self.round_keys, &mut self.block) };
+                F::increment(&mut self.counter);
+            }
+        }
+
+        impl<F: CtrFlavor> NewStreamCipher for $name<F> {
+            type KeySize = $key_size;
+            type NonceSize = U16;
+
+            fn new(
+                key: &GenericArray<u8, Self::KeySize>,
+                nonce: &GenericArray<u8, Self::NonceSize>,
+            ) -> Self {
+                let expanded = expand_key(key.as_slice(), $key_words, $rounds);
+                let mut round_keys = [unsafe { mem::zeroed() }; $rounds + 1];
+                for (rk, bytes) in round_keys.iter_mut().zip(expanded.iter()) {
+                    *rk = load_round_key(bytes);
+                }
+                let mut nonce_block = [0u8; BLOCK_SIZE];
+                nonce_block.copy_from_slice(nonce.as_slice());
+                let counter = F::counter_at(&nonce_block, 0);
+                Self {
+                    round_keys,
+                    nonce: nonce_block,
+                    counter,
+                    block: [0u8; BLOCK_SIZE],
+                    pos: BLOCK_SIZE as u8,
+                    _flavor: PhantomData,
+                }
+            }
+
+            fn new_var(key: &[u8], nonce: &[u8]) -> Result<Self, InvalidKeyNonceLength> {
+                if key.len() != Self::key_size() || nonce.len() != Self::nonce_size() {
+                    return Err(InvalidKeyNonceLength);
+                }
+                Ok(Self::new(
+                    GenericArray::from_slice(key),
+                    GenericArray::from_slice(nonce),
+                ))
+            }
+        }
+
+        impl<F: CtrFlavor> SyncStreamCipher for $name<F> {
+            fn apply_keystream(&mut self, mut data: &mut [u8]) {
+                while !data.is_empty() {
+                    if self.pos as usize == BLOCK_SIZE {
+                        self.keystream_block();
+                        self.pos = 0;
+                    }
+                    let n = core::cmp::min(data.len(), BLOCK_SIZE - self.pos as usize);
+                    for (b, k) in data[..n]
+                        .iter_mut()
+                        .zip(self.block[self.pos as usize..].iter())
+                    {
+                        *b ^= k;
+                    }
+                    self.pos += n as u8;
+                    data = &mut data[n..];
+                }
+            }
+        }
+
+        impl<F: CtrFlavor> SyncStreamCipherSeek for $name<F> {
+            fn current_pos(&self) -> u64 {
+                // `self.counter` always holds the block *after* the one last
+                // written into `self.block` (it's incremented eagerly by
+                // `keystream_block`), so when `pos == BLOCK_SIZE` (no bytes
+                // of the current block consumed yet, including the initial
+                // state before any block has been generated) it already
+                // names the right block and must not be shifted back by one.
+                let block_index = F::block_index(&self.nonce, &self.counter);
+                if self.pos as usize == BLOCK_SIZE {
+                    block_index as u64 * BLOCK_SIZE as u64
+                } else {
+                    // Not a plain `block_index - 1`: once a narrow counter
+                    // field has wrapped back to the nonce's value,
+                    // `block_index` is `0`, and subtracting one as a `u128`
+                    // would overflow the multiply below.
+                    F::previous_block_index(block_index) as u64 * BLOCK_SIZE as u64
+                        + self.pos as u64
+                }
+            }
+
+            fn seek(&mut self, pos: u64) {
+                let block = pos / BLOCK_SIZE as u64;
+                self.counter = F::counter_at(&self.nonce, block as u128);
+                self.keystream_block();
+                self.pos = (pos % BLOCK_SIZE as u64) as u8;
+            }
+        }
+
+        impl<F> core::fmt::Debug for $name<F> {
+            fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+                // Deliberately don't print round keys or counter/nonce state.
+                f.debug_struct(stringify!($name)).finish_non_exhaustive()
+            }
+        }
+    };
+}
+
+impl_armv8_ctr!(
+    Aes128Ctr,
+    U16,
+    4,
+    10,
+    "AES-128 in CTR mode, accelerated with ARMv8 Cryptography Extensions"
+);
+impl_armv8_ctr!(
+    Aes192Ctr,
+    U24,
+    6,
+    12,
+    "AES-192 in CTR mode, accelerated with ARMv8 Cryptography Extensions"
+);
+impl_armv8_ctr!(
+    Aes256Ctr,
+    U32,
+    8,
+    14,
+    "AES-256 in CTR mode, accelerated with ARMv8 Cryptography Extensions"
+);
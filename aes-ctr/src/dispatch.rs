@@ -0,0 +1,119 @@
+//! Runtime CPU feature detection for the x86/x86_64 backends.
+//!
+//! The compile-time selection in `lib.rs` assumes the final binary will only
+//! ever run on hosts with `aes`/`sse2`/`ssse3` support when those target
+//! features are enabled via `RUSTFLAGS`. That assumption breaks for binaries
+//! distributed to a fleet of heterogeneous machines: built with the features
+//! on, they raise `SIGILL` on older hosts; built with them off, newer hosts
+//! never get hardware acceleration. The types in this module probe CPU
+//! support once (cached thereafter) and dispatch to whichever keystream
+//! generator the running CPU actually supports.
+//!
+//! `is_x86_feature_detected!` is a `std`-only macro, unusable in this
+//! `#![no_std]` crate, so detection goes through the `cpufeatures` crate
+//! instead -- the same `no_std`-compatible, cache-after-first-check approach
+//! the rest of RustCrypto uses.
+
+use crate::cipher::{
+    errors::InvalidKeyNonceLength,
+    generic_array::{
+        typenum::{U16, U24, U32},
+        GenericArray,
+    },
+    stream::{NewStreamCipher, SyncStreamCipher, SyncStreamCipherSeek},
+};
+use crate::flavors::{Ctr128BE, CtrFlavor};
+
+mod ni;
+
+cpufeatures::new!(aes_intrinsics, "aes", "sse2", "ssse3");
+
+macro_rules! impl_dispatch_ctr {
+    ($name:ident, $soft_name:ident, $ni_name:ident, $key_size:ty, $doc:expr) => {
+        #[doc = $doc]
+        ///
+        /// Generic over the counter-block policy `F` (see [`crate::flavors`]);
+        /// defaults to [`Ctr128BE`], this crate's historical full-width
+        /// big-endian counter.
+        #[derive(Debug)]
+        pub enum $name<F: CtrFlavor = Ctr128BE> {
+            #[doc(hidden)]
+            Soft(crate::soft::$soft_name<F>),
+            #[doc(hidden)]
+            Ni(ni::$ni_name<F>),
+        }
+
+        impl<F: CtrFlavor> NewStreamCipher for $name<F> {
+            type KeySize = $key_size;
+            type NonceSize = U16;
+
+            fn new(
+                key: &GenericArray<u8, Self::KeySize>,
+                nonce: &GenericArray<u8, Self::NonceSize>,
+            ) -> Self {
+                if aes_intrinsics::init().get() {
+                    $name::Ni(ni::$ni_name::new(key, nonce))
+                } else {
+                    $name::Soft(crate::soft::$soft_name::new(key, nonce))
+                }
+            }
+
+            fn new_var(key: &[u8], nonce: &[u8]) -> Result<Self, InvalidKeyNonceLength> {
+                if key.len() != Self::key_size() || nonce.len() != Self::nonce_size() {
+                    return Err(InvalidKeyNonceLength);
+                }
+                Ok(Self::new(
+                    GenericArray::from_slice(key),
+                    GenericArray::from_slice(nonce),
+                ))
+            }
+        }
+
+        impl<F: CtrFlavor> SyncStreamCipher for $name<F> {
+            fn apply_keystream(&mut self, data: &mut [u8]) {
+                match self {
+                    $name::Soft(c) => c.apply_keystream(data),
+                    $name::Ni(c) => c.apply_keystream(data),
+                }
+            }
+        }
+
+        impl<F: CtrFlavor> SyncStreamCipherSeek for $name<F> {
+            fn current_pos(&self) -> u64 {
+                match self {
+                    $name::Soft(c) => c.current_pos(),
+                    $name::Ni(c) => c.current_pos(),
+                }
+            }
+
+            fn seek(&mut self, pos: u64) {
+                match self {
+                    $name::Soft(c) => c.seek(pos),
+                    $name::Ni(c) => c.seek(pos),
+                }
+            }
+        }
+    };
+}
+
+impl_dispatch_ctr!(
+    Aes128Ctr,
+    Aes128Ctr,
+    Aes128Ctr,
+    U16,
+    "AES-128 in CTR mode, dispatching to AES-NI or the portable software backend depending on what the running CPU supports"
+);
+impl_dispatch_ctr!(
+    Aes192Ctr,
+    Aes192Ctr,
+    Aes192Ctr,
+    U24,
+    "AES-192 in CTR mode, dispatching to AES-NI or the portable software backend depending on what the running CPU supports"
+);
+impl_dispatch_ctr!(
+    Aes256Ctr,
+    Aes256Ctr,
+    Aes256Ctr,
+    U32,
+    "AES-256 in CTR mode, dispatching to AES-NI or the portable software backend depending on what the running CPU supports"
+);
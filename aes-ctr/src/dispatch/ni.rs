@@ -0,0 +1,182 @@
+//! Self-contained AES-NI block cipher + CTR mode, compiled with
+//! `#[target_feature]` so it can be included in a binary regardless of the
+//! crate-wide compile-time target features and only ever invoked after
+//! [`super`] has confirmed the running CPU actually supports `aes`/`sse2`/
+//! `ssse3`.
+
+use crate::cipher::{
+    errors::InvalidKeyNonceLength,
+    generic_array::{
+        typenum::{U16, U24, U32},
+        GenericArray,
+    },
+    stream::{NewStreamCipher, SyncStreamCipher, SyncStreamCipherSeek},
+};
+use crate::flavors::{Ctr128BE, CtrFlavor};
+use crate::key_schedule::expand_key;
+
+#[cfg(target_arch = "x86")]
+use core::arch::x86::*;
+#[cfg(target_arch = "x86_64")]
+use core::arch::x86_64::*;
+use core::marker::PhantomData;
+
+const BLOCK_SIZE: usize = 16;
+
+#[target_feature(enable = "aes,sse2,ssse3")]
+unsafe fn encrypt_block(round_keys: &[__m128i], block: &mut [u8; BLOCK_SIZE]) {
+    let mut state = _mm_loadu_si128(block.as_ptr() as *const __m128i);
+    let last = round_keys.len() - 1;
+
+    state = _mm_xor_si128(state, round_keys[0]);
+    for rk in &round_keys[1..last] {
+        state = _mm_aesenc_si128(state, *rk);
+    }
+    state = _mm_aesenclast_si128(state, round_keys[last]);
+
+    _mm_storeu_si128(block.as_mut_ptr() as *mut __m128i, state);
+}
+
+#[target_feature(enable = "aes,sse2,ssse3")]
+unsafe fn load_round_key(bytes: &[u8; BLOCK_SIZE]) -> __m128i {
+    _mm_loadu_si128(bytes.as_ptr() as *const __m128i)
+}
+
+macro_rules! impl_ni_ctr {
+    ($name:ident, $key_size:ty, $key_words:expr, $rounds:expr, $doc:expr) => {
+        #[doc = $doc]
+        ///
+        /// Generic over the counter-block policy `F` (see [`crate::flavors`]);
+        /// defaults to [`Ctr128BE`], this crate's historical full-width
+        /// big-endian counter.
+        pub struct $name<F = Ctr128BE> {
+            round_keys: [__m128i; $rounds + 1],
+            nonce: [u8; BLOCK_SIZE],
+            counter: [u8; BLOCK_SIZE],
+            block: [u8; BLOCK_SIZE],
+            pos: u8,
+            _flavor: PhantomData<F>,
+        }
+
+        impl<F: CtrFlavor> $name<F> {
+            fn keystream_block(&mut self) {
+                self.block = self.counter;
+                unsafe { encrypt_block(&self.round_keys, &mut self.block) };
+                F::increment(&mut self.counter);
+            }
+        }
+
+        impl<F: CtrFlavor> NewStreamCipher for $name<F> {
+            type KeySize = $key_size;
+            type NonceSize = U16;
+
+            fn new(
+                key: &GenericArray<u8, Self::KeySize>,
+                nonce: &GenericArray<u8, Self::NonceSize>,
+            ) -> Self {
+                let expanded = expand_key(key.as_slice(), $key_words, $rounds);
+                let mut round_keys = [unsafe { _mm_setzero_si128() }; $rounds + 1];
+                for (rk, bytes) in round_keys.iter_mut().zip(expanded.iter()) {
+                    *rk = unsafe { load_round_key(bytes) };
+                }
+                let mut nonce_block = [0u8; BLOCK_SIZE];
+                nonce_block.copy_from_slice(nonce.as_slice());
+                let counter = F::counter_at(&nonce_block, 0);
+                Self {
+                    round_keys,
+                    nonce: nonce_block,
+                    counter,
+                    block: [0u8; BLOCK_SIZE],
+                    pos: BLOCK_SIZE as u8,
+                    _flavor: PhantomData,
+                }
+            }
+
+            fn new_var(key: &[u8], nonce: &[u8]) -> Result<Self, InvalidKeyNonceLength> {
+                if key.len() != Self::key_size() || nonce.len() != Self::nonce_size() {
+                    return Err(InvalidKeyNonceLength);
+                }
+                Ok(Self::new(
+                    GenericArray::from_slice(key),
+                    GenericArray::from_slice(nonce),
+                ))
+            }
+        }
+
+        impl<F: CtrFlavor> SyncStreamCipher for $name<F> {
+            fn apply_keystream(&mut self, mut data: &mut [u8]) {
+                while !data.is_empty() {
+                    if self.pos as usize == BLOCK_SIZE {
+                        self.keystream_block();
+                        self.pos = 0;
+                    }
+                    let n = core::cmp::min(data.len(), BLOCK_SIZE - self.pos as usize);
+                    for (b, k) in data[..n]
+                        .iter_mut()
+                        .zip(self.block[self.pos as usize..].iter())
+                    {
+                        *b ^= k;
+                    }
+                    self.pos += n as u8;
+                    data = &mut data[n..];
+                }
+            }
+        }
+
+        impl<F: CtrFlavor> SyncStreamCipherSeek for $name<F> {
+            fn current_pos(&self) -> u64 {
+                // See the identical comment in `armv8.rs`: `self.counter` is
+                // already one block ahead of `self.block` by the time this
+                // runs, so the `pos == BLOCK_SIZE` sentinel (including the
+                // pre-first-block state) must not be shifted back by one.
+                let block_index = F::block_index(&self.nonce, &self.counter);
+                if self.pos as usize == BLOCK_SIZE {
+                    block_index as u64 * BLOCK_SIZE as u64
+                } else {
+                    // Not a plain `block_index - 1`: once a narrow counter
+                    // field has wrapped back to the nonce's value,
+                    // `block_index` is `0`, and subtracting one as a `u128`
+                    // would overflow the multiply below.
+                    F::previous_block_index(block_index) as u64 * BLOCK_SIZE as u64
+                        + self.pos as u64
+                }
+            }
+
+            fn seek(&mut self, pos: u64) {
+                let block = pos / BLOCK_SIZE as u64;
+                self.counter = F::counter_at(&self.nonce, block as u128);
+                self.keystream_block();
+                self.pos = (pos % BLOCK_SIZE as u64) as u8;
+            }
+        }
+
+        impl<F> core::fmt::Debug for $name<F> {
+            fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+                // Deliberately don't print round keys or counter/nonce state.
+                f.debug_struct(stringify!($name)).finish_non_exhaustive()
+            }
+        }
+    };
+}
+
+impl_ni_ctr!(
+    Aes128Ctr,
+    U16,
+    4,
+    10,
+    "AES-128 in CTR mode, hardware-accelerated with AES-NI"
+);
+impl_ni_ctr!(
+    Aes192Ctr,
+    U24,
+    6,
+    12,
+    "AES-192 in CTR mode, hardware-accelerated with AES-NI"
+);
+impl_ni_ctr!(
+    Aes256Ctr,
+    U32,
+    8,
+    14,
+    "AES-256 in CTR mode, hardware-accelerated with AES-NI"
+);
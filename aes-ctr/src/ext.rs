@@ -0,0 +1,101 @@
+//! One-shot and incremental convenience helpers over `&mut [u8]` buffers.
+//!
+//! Inspired by the single-call `encrypt`/`decrypt` surface of the OpenSSL
+//! `symm` and BoringSSL `EVP` bindings, [`StreamCipherExt`] lets callers skip
+//! wiring up `new`/`new_var` and `apply_keystream` by hand for the common
+//! case of encrypting (or decrypting -- CTR mode is its own inverse) one
+//! buffer with one key and nonce.
+//!
+//! ```
+//! use aes_ctr::{Aes128Ctr, StreamCipherExt};
+//! use aes_ctr::cipher::generic_array::GenericArray;
+//!
+//! let key = GenericArray::from_slice(b"very secret key.");
+//! let nonce = GenericArray::from_slice(b"and secret nonce");
+//! let mut data = *b"hello, world!!!!";
+//!
+//! Aes128Ctr::apply(key, nonce, &mut data);
+//! ```
+//!
+//! For streaming/chunked processing (e.g. decrypting a large file one
+//! buffer at a time), construct the cipher once with `new`/`new_var` and
+//! call `apply_keystream` per chunk; [`SyncStreamCipherSeek::seek`] lets you
+//! jump to an arbitrary byte offset (e.g. to support random access reads)
+//! without having to replay the keystream from the start:
+//!
+//! ```
+//! use aes_ctr::Aes128Ctr;
+//! use aes_ctr::cipher::{generic_array::GenericArray, stream::{NewStreamCipher, SyncStreamCipher, SyncStreamCipherSeek}};
+//!
+//! let key = GenericArray::from_slice(b"very secret key.");
+//! let nonce = GenericArray::from_slice(b"and secret nonce");
+//! let mut cipher = Aes128Ctr::new(key, nonce);
+//!
+//! let mut chunk_a = [0u8; 16];
+//! let mut chunk_b = [0u8; 16];
+//! cipher.apply_keystream(&mut chunk_a);
+//! cipher.apply_keystream(&mut chunk_b);
+//!
+//! // re-read the second chunk later without touching the first
+//! cipher.seek(16);
+//! let mut chunk_b_again = [0u8; 16];
+//! cipher.apply_keystream(&mut chunk_b_again);
+//! assert_eq!(chunk_b, chunk_b_again);
+//! ```
+
+use crate::cipher::{
+    errors::InvalidKeyNonceLength,
+    generic_array::GenericArray,
+    stream::{NewStreamCipher, SyncStreamCipher},
+};
+
+/// One-shot `apply`/`apply_var` helpers, blanket-implemented for every
+/// stream cipher (not just the ones in this crate) that implements
+/// [`NewStreamCipher`] and [`SyncStreamCipher`].
+pub trait StreamCipherExt: NewStreamCipher + SyncStreamCipher + Sized {
+    /// Construct the cipher from `key`/`nonce`, apply its keystream to
+    /// `buf` in place, and drop it.
+    fn apply(
+        key: &GenericArray<u8, Self::KeySize>,
+        nonce: &GenericArray<u8, Self::NonceSize>,
+        buf: &mut [u8],
+    ) {
+        Self::new(key, nonce).apply_keystream(buf);
+    }
+
+    /// Like [`apply`](Self::apply), but with runtime-checked key/nonce
+    /// lengths for callers that don't have `GenericArray`s on hand.
+    fn apply_var(key: &[u8], nonce: &[u8], buf: &mut [u8]) -> Result<(), InvalidKeyNonceLength> {
+        let mut cipher = Self::new_var(key, nonce)?;
+        cipher.apply_keystream(buf);
+        Ok(())
+    }
+}
+
+impl<T: NewStreamCipher + SyncStreamCipher> StreamCipherExt for T {}
+
+#[test]
+fn apply_var_matches_a_manually_driven_cipher() {
+    use crate::Aes128Ctr;
+
+    let key = *b"very secret key.";
+    let nonce = *b"and secret nonce";
+
+    let mut expected = *b"hello, world, this is more than one block!!!!!";
+    let mut cipher = Aes128Ctr::new_var(&key, &nonce).unwrap();
+    cipher.apply_keystream(&mut expected);
+
+    let mut actual = *b"hello, world, this is more than one block!!!!!";
+    Aes128Ctr::apply_var(&key, &nonce, &mut actual).unwrap();
+
+    assert_eq!(actual, expected);
+}
+
+#[test]
+fn apply_var_rejects_invalid_lengths() {
+    use crate::Aes128Ctr;
+
+    let mut buf = [0u8; 16];
+    assert!(Aes128Ctr::apply_var(&[0u8; 15], &[0u8; 16], &mut buf).is_err());
+    assert!(Aes128Ctr::apply_var(&[0u8; 16], &[0u8; 15], &mut buf).is_err());
+}
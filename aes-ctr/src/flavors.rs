@@ -0,0 +1,135 @@
+//! Counter-block policies controlling how wide the incrementing counter is
+//! and where it sits within the 128-bit CTR block.
+//!
+//! [`Ctr128BE`] is this crate's historical, default behavior: the whole
+//! 128-bit block doubles as one big-endian counter that wraps at 2^128, as
+//! exercised by the OpenSSL comparison tests in `lib.rs`. Protocols built on
+//! RFC 3686 / SP 800-38A (e.g. IPsec ESP) instead fix a per-message nonce in
+//! the high bytes of the block and only increment a narrower 32- or 64-bit
+//! counter in the low bytes, so that wraparound is confined to the counter
+//! field and never corrupts the nonce. The other types here implement that
+//! narrower style; the counter field is always the low-order bytes of the
+//! block, matching RFC 3686.
+
+/// A policy for how the 128-bit CTR counter block is built and incremented.
+pub trait CtrFlavor {
+    /// Increment the counter block in place, wrapping only within the
+    /// configured counter field.
+    fn increment(counter: &mut [u8; 16]);
+
+    /// Build the counter block for the `block`-th keystream block given the
+    /// nonce the cipher was constructed with.
+    fn counter_at(nonce: &[u8; 16], block: u128) -> [u8; 16];
+
+    /// Recover the keystream block index of `counter`, given the nonce the
+    /// cipher was constructed with. Inverse of [`counter_at`](Self::counter_at).
+    fn block_index(nonce: &[u8; 16], counter: &[u8; 16]) -> u128;
+
+    /// Subtract one from a block index returned by [`block_index`
+    /// ](Self::block_index), wrapping within this flavor's own counter
+    /// width rather than the full 128 bits `block_index` is expressed in.
+    /// Needed because once a narrow counter field has itself wrapped back
+    /// to the nonce's value, `block_index` returns `0`; naively subtracting
+    /// one as a `u128` would yield `u128::MAX` and overflow the byte-offset
+    /// math in `current_pos`.
+    fn previous_block_index(index: u128) -> u128;
+}
+
+/// Treat the whole 128-bit block as one big-endian counter that wraps at
+/// 2^128 (this crate's default, historical behavior).
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Ctr128BE;
+
+impl CtrFlavor for Ctr128BE {
+    fn increment(counter: &mut [u8; 16]) {
+        for byte in counter.iter_mut().rev() {
+            *byte = byte.wrapping_add(1);
+            if *byte != 0 {
+                break;
+            }
+        }
+    }
+
+    fn counter_at(nonce: &[u8; 16], block: u128) -> [u8; 16] {
+        u128::from_be_bytes(*nonce).wrapping_add(block).to_be_bytes()
+    }
+
+    fn block_index(nonce: &[u8; 16], counter: &[u8; 16]) -> u128 {
+        u128::from_be_bytes(*counter).wrapping_sub(u128::from_be_bytes(*nonce))
+    }
+
+    fn previous_block_index(index: u128) -> u128 {
+        index.wrapping_sub(1)
+    }
+}
+
+macro_rules! impl_partial_width_flavor {
+    ($name:ident, $uint:ty, $width:expr, $from_bytes:ident, $to_bytes:ident, $doc:expr) => {
+        #[doc = $doc]
+        #[derive(Clone, Copy, Debug, Default)]
+        pub struct $name;
+
+        impl CtrFlavor for $name {
+            fn increment(counter: &mut [u8; 16]) {
+                let mut bytes = [0u8; $width];
+                bytes.copy_from_slice(&counter[16 - $width..]);
+                let n = <$uint>::$from_bytes(bytes).wrapping_add(1);
+                counter[16 - $width..].copy_from_slice(&n.$to_bytes());
+            }
+
+            fn counter_at(nonce: &[u8; 16], block: u128) -> [u8; 16] {
+                let mut counter = *nonce;
+                let mut bytes = [0u8; $width];
+                bytes.copy_from_slice(&nonce[16 - $width..]);
+                let n = <$uint>::$from_bytes(bytes).wrapping_add(block as $uint);
+                counter[16 - $width..].copy_from_slice(&n.$to_bytes());
+                counter
+            }
+
+            fn block_index(nonce: &[u8; 16], counter: &[u8; 16]) -> u128 {
+                let mut n_bytes = [0u8; $width];
+                n_bytes.copy_from_slice(&nonce[16 - $width..]);
+                let mut c_bytes = [0u8; $width];
+                c_bytes.copy_from_slice(&counter[16 - $width..]);
+                <$uint>::$from_bytes(c_bytes).wrapping_sub(<$uint>::$from_bytes(n_bytes)) as u128
+            }
+
+            fn previous_block_index(index: u128) -> u128 {
+                (index as $uint).wrapping_sub(1) as u128
+            }
+        }
+    };
+}
+
+impl_partial_width_flavor!(
+    Ctr32BE,
+    u32,
+    4,
+    from_be_bytes,
+    to_be_bytes,
+    "RFC 3686 style: only the low 32 bits of the block are a big-endian counter; the high 96 bits (nonce/IV) never change."
+);
+impl_partial_width_flavor!(
+    Ctr64BE,
+    u64,
+    8,
+    from_be_bytes,
+    to_be_bytes,
+    "Like `Ctr32BE` but with a 64-bit counter field."
+);
+impl_partial_width_flavor!(
+    Ctr32LE,
+    u32,
+    4,
+    from_le_bytes,
+    to_le_bytes,
+    "Like `Ctr32BE` but the counter field is little-endian."
+);
+impl_partial_width_flavor!(
+    Ctr64LE,
+    u64,
+    8,
+    from_le_bytes,
+    to_le_bytes,
+    "Like `Ctr64BE` but the counter field is little-endian."
+);
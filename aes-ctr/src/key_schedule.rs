@@ -0,0 +1,157 @@
+//! Portable AES primitives shared by the hardware-accelerated backends and
+//! by [`crate::aes_siv`]'s CMAC construction.
+//!
+//! None of the ARMv8 or x86 Cryptography Extension instructions accelerate
+//! key expansion (only the per-round S-box/MixColumns step), so every
+//! hardware backend needs the same plain software key schedule; it lives
+//! here once instead of being copy-pasted into each backend module. This
+//! module also provides a fully portable (no intrinsics) single-block
+//! encryption routine, which `aes_siv`'s CMAC uses on every backend: its
+//! per-block cipher calls are not hardware-accelerated the way the bulk
+//! keystream is.
+
+const BLOCK_SIZE: usize = 16;
+
+const RCON: [u8; 10] = [0x01, 0x02, 0x04, 0x08, 0x10, 0x20, 0x40, 0x80, 0x1b, 0x36];
+
+#[rustfmt::skip]
+const SBOX: [u8; 256] = [
+    0x63, 0x7c, 0x77, 0x7b, 0xf2, 0x6b, 0x6f, 0xc5, 0x30, 0x01, 0x67, 0x2b, 0xfe, 0xd7, 0xab, 0x76,
+    0xca, 0x82, 0xc9, 0x7d, 0xfa, 0x59, 0x47, 0xf0, 0xad, 0xd4, 0xa2, 0xaf, 0x9c, 0xa4, 0x72, 0xc0,
+    0xb7, 0xfd, 0x93, 0x26, 0x36, 0x3f, 0xf7, 0xcc, 0x34, 0xa5, 0xe5, 0xf1, 0x71, 0xd8, 0x31, 0x15,
+    0x04, 0xc7, 0x23, 0xc3, 0x18, 0x96, 0x05, 0x9a, 0x07, 0x12, 0x80, 0xe2, 0xeb, 0x27, 0xb2, 0x75,
+    0x09, 0x83, 0x2c, 0x1a, 0x1b, 0x6e, 0x5a, 0xa0, 0x52, 0x3b, 0xd6, 0xb3, 0x29, 0xe3, 0x2f, 0x84,
+    0x53, 0xd1, 0x00, 0xed, 0x20, 0xfc, 0xb1, 0x5b, 0x6a, 0xcb, 0xbe, 0x39, 0x4a, 0x4c, 0x58, 0xcf,
+    0xd0, 0xef, 0xaa, 0xfb, 0x43, 0x4d, 0x33, 0x85, 0x45, 0xf9, 0x02, 0x7f, 0x50, 0x3c, 0x9f, 0xa8,
+    0x51, 0xa3, 0x40, 0x8f, 0x92, 0x9d, 0x38, 0xf5, 0xbc, 0xb6, 0xda, 0x21, 0x10, 0xff, 0xf3, 0xd2,
+    0xcd, 0x0c, 0x13, 0xec, 0x5f, 0x97, 0x44, 0x17, 0xc4, 0xa7, 0x7e, 0x3d, 0x64, 0x5d, 0x19, 0x73,
+    0x60, 0x81, 0x4f, 0xdc, 0x22, 0x2a, 0x90, 0x88, 0x46, 0xee, 0xb8, 0x14, 0xde, 0x5e, 0x0b, 0xdb,
+    0xe0, 0x32, 0x3a, 0x0a, 0x49, 0x06, 0x24, 0x5c, 0xc2, 0xd3, 0xac, 0x62, 0x91, 0x95, 0xe4, 0x79,
+    0xe7, 0xc8, 0x37, 0x6d, 0x8d, 0xd5, 0x4e, 0xa9, 0x6c, 0x56, 0xf4, 0xea, 0x65, 0x7a, 0xae, 0x08,
+    0xba, 0x78, 0x25, 0x2e, 0x1c, 0xa6, 0xb4, 0xc6, 0xe8, 0xdd, 0x74, 0x1f, 0x4b, 0xbd, 0x8b, 0x8a,
+    0x70, 0x3e, 0xb5, 0x66, 0x48, 0x03, 0xf6, 0x0e, 0x61, 0x35, 0x57, 0xb9, 0x86, 0xc1, 0x1d, 0x9e,
+    0xe1, 0xf8, 0x98, 0x11, 0x69, 0xd9, 0x8e, 0x94, 0x9b, 0x1e, 0x87, 0xe9, 0xce, 0x55, 0x28, 0xdf,
+    0x8c, 0xa1, 0x89, 0x0d, 0xbf, 0xe6, 0x42, 0x68, 0x41, 0x99, 0x2d, 0x0f, 0xb0, 0x54, 0xbb, 0x16,
+];
+
+fn sub_word(word: [u8; 4]) -> [u8; 4] {
+    [
+        SBOX[word[0] as usize],
+        SBOX[word[1] as usize],
+        SBOX[word[2] as usize],
+        SBOX[word[3] as usize],
+    ]
+}
+
+fn rot_word(word: [u8; 4]) -> [u8; 4] {
+    [word[1], word[2], word[3], word[0]]
+}
+
+/// Expand `key` (`key_words` 32-bit words) into `rounds + 1` round keys of
+/// 16 bytes each.
+pub(crate) fn expand_key(key: &[u8], key_words: usize, rounds: usize) -> [[u8; BLOCK_SIZE]; 15] {
+    let total_words = 4 * (rounds + 1);
+    let mut w = [[0u8; 4]; 60];
+
+    for i in 0..key_words {
+        w[i] = [key[4 * i], key[4 * i + 1], key[4 * i + 2], key[4 * i + 3]];
+    }
+
+    for i in key_words..total_words {
+        let mut temp = w[i - 1];
+        if i % key_words == 0 {
+            temp = sub_word(rot_word(temp));
+            temp[0] ^= RCON[i / key_words - 1];
+        } else if key_words > 6 && i % key_words == 4 {
+            temp = sub_word(temp);
+        }
+        w[i] = [
+            w[i - key_words][0] ^ temp[0],
+            w[i - key_words][1] ^ temp[1],
+            w[i - key_words][2] ^ temp[2],
+            w[i - key_words][3] ^ temp[3],
+        ];
+    }
+
+    let mut round_keys = [[0u8; BLOCK_SIZE]; 15];
+    for (i, rk) in round_keys.iter_mut().enumerate().take(rounds + 1) {
+        for j in 0..4 {
+            rk[4 * j..4 * j + 4].copy_from_slice(&w[4 * i + j]);
+        }
+    }
+    round_keys
+}
+
+fn xtime(x: u8) -> u8 {
+    let shifted = x << 1;
+    if x & 0x80 != 0 {
+        shifted ^ 0x1b
+    } else {
+        shifted
+    }
+}
+
+fn sub_bytes(state: &mut [u8; BLOCK_SIZE]) {
+    for b in state.iter_mut() {
+        *b = SBOX[*b as usize];
+    }
+}
+
+#[rustfmt::skip]
+const SHIFT_ROWS: [usize; BLOCK_SIZE] = [
+    0, 5, 10, 15,
+    4, 9, 14, 3,
+    8, 13, 2, 7,
+    12, 1, 6, 11,
+];
+
+fn shift_rows(state: &[u8; BLOCK_SIZE]) -> [u8; BLOCK_SIZE] {
+    let mut out = [0u8; BLOCK_SIZE];
+    for (o, &i) in out.iter_mut().zip(SHIFT_ROWS.iter()) {
+        *o = state[i];
+    }
+    out
+}
+
+fn mix_columns(state: &[u8; BLOCK_SIZE]) -> [u8; BLOCK_SIZE] {
+    let mut out = [0u8; BLOCK_SIZE];
+    for c in 0..4 {
+        let a = [
+            state[4 * c],
+            state[4 * c + 1],
+            state[4 * c + 2],
+            state[4 * c + 3],
+        ];
+        out[4 * c] = xtime(a[0]) ^ xtime(a[1]) ^ a[1] ^ a[2] ^ a[3];
+        out[4 * c + 1] = a[0] ^ xtime(a[1]) ^ xtime(a[2]) ^ a[2] ^ a[3];
+        out[4 * c + 2] = a[0] ^ a[1] ^ xtime(a[2]) ^ xtime(a[3]) ^ a[3];
+        out[4 * c + 3] = xtime(a[0]) ^ a[0] ^ a[1] ^ a[2] ^ xtime(a[3]);
+    }
+    out
+}
+
+fn add_round_key(state: &mut [u8; BLOCK_SIZE], round_key: &[u8; BLOCK_SIZE]) {
+    for (s, k) in state.iter_mut().zip(round_key.iter()) {
+        *s ^= k;
+    }
+}
+
+/// Encrypt `block` in place with a fully portable (no intrinsics) AES
+/// implementation, using `rounds + 1` entries of `round_keys` as produced by
+/// [`expand_key`].
+pub(crate) fn encrypt_block_portable(
+    round_keys: &[[u8; BLOCK_SIZE]],
+    rounds: usize,
+    block: &mut [u8; BLOCK_SIZE],
+) {
+    add_round_key(block, &round_keys[0]);
+    for rk in &round_keys[1..rounds] {
+        sub_bytes(block);
+        *block = shift_rows(block);
+        *block = mix_columns(block);
+        add_round_key(block, rk);
+    }
+    sub_bytes(block);
+    *block = shift_rows(block);
+    add_round_key(block, &round_keys[rounds]);
+}
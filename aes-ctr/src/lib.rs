@@ -3,15 +3,41 @@
 //! Cipher functionality is accessed using traits from re-exported
 //! [`cipher`](https://docs.rs/cipher) crate.
 //!
-//! This crate will select appropriate implementation at compile time depending
-//! on target architecture and enabled target features. For the best performance
-//! on x86-64 CPUs enable `aes`, `sse2` and `ssse3` target features. You can do
-//! it either by using `RUSTFLAGS="-C target-feature=+aes,+ssse3"` or by editing
-//! your `.cargo/config`. (`sse2` target feature is usually enabled by default)
+//! On x86/x86_64, by default this crate probes for AES-NI support once at
+//! cipher construction time and transparently falls back to the portable
+//! software backend on hosts that lack it, so a single binary stays both
+//! portable and accelerated. If the `aes`, `sse2` and `ssse3` target
+//! features are enabled at compile time (e.g. via
+//! `RUSTFLAGS="-C target-feature=+aes,+ssse3"` or your `.cargo/config`,
+//! `sse2` is usually enabled by default), the AES-NI backend is selected at
+//! compile time and no runtime check is made. Enabling the
+//! `force_static_dispatch` feature turns the runtime check off for builds
+//! without those target features as well, returning to the old purely
+//! compile-time selection (the portable software backend).
+//!
+//! On `aarch64` targets enabling the `aes` target feature (`RUSTFLAGS="-C
+//! target-feature=+aes"`) selects the ARMv8 Cryptography Extension backend.
+//!
+//! `Aes128Ctr`, `Aes192Ctr` and `Aes256Ctr` are generic over a counter-block
+//! policy (the [`flavors`] module) and default to [`Ctr128BE`], which treats
+//! the whole 128-bit nonce as one big-endian counter wrapping at 2^128. RFC
+//! 3686 / SP 800-38A style protocols that fix a per-message nonce in the
+//! high bytes and only increment a 32- or 64-bit counter in the low bytes
+//! can select e.g. `Aes128Ctr::<Ctr32BE>` instead. When the `aes`, `sse2`
+//! and `ssse3` target features are enabled at compile time on x86/x86_64,
+//! these names are the `aesni` crate's ciphers instead, which take no
+//! counter-block parameter.
+//!
+//! For one-shot encryption (or decryption -- it's the same operation) of a
+//! whole buffer, [`StreamCipherExt::apply`] and [`StreamCipherExt::apply_var`]
+//! skip the `new`/`apply_keystream` boilerplate. For chunked/streaming
+//! processing, construct the cipher once, call `apply_keystream` per chunk,
+//! and use `SyncStreamCipherSeek::seek` to jump to an arbitrary byte offset.
 //!
 //! # Security Warning
 //! This crate does not ensure ciphertexts are authentic! Thus ciphertext integrity
-//! is not verified, which can lead to serious vulnerabilities!
+//! is not verified, which can lead to serious vulnerabilities! If you need
+//! authentication and resistance to nonce reuse, see [`aes_siv`] instead.
 //!
 //! # Usage example
 //! ```
@@ -49,19 +75,73 @@
 
 pub use cipher;
 
-#[cfg(not(all(
-    target_feature = "aes",
-    target_feature = "sse2",
-    target_feature = "ssse3",
-    any(target_arch = "x86_64", target_arch = "x86"),
+#[cfg(all(target_arch = "aarch64", target_feature = "aes"))]
+mod armv8;
+
+#[cfg(not(any(
+    all(
+        target_feature = "aes",
+        target_feature = "sse2",
+        target_feature = "ssse3",
+        any(target_arch = "x86_64", target_arch = "x86"),
+    ),
+    all(target_arch = "aarch64", target_feature = "aes"),
 )))]
 mod soft;
 
-#[cfg(not(all(
-    target_feature = "aes",
-    target_feature = "sse2",
-    target_feature = "ssse3",
+mod ext;
+mod flavors;
+mod key_schedule;
+
+pub use crate::ext::StreamCipherExt;
+pub use crate::flavors::{Ctr128BE, Ctr32BE, Ctr32LE, Ctr64BE, Ctr64LE, CtrFlavor};
+
+pub mod aes_siv;
+
+// Probed once at construction time instead of baked in by `RUSTFLAGS`: lets a
+// single binary use AES-NI on hosts that have it and fall back to the
+// portable software backend on hosts that don't. Opt out with the
+// `force_static_dispatch` feature to get the old, purely compile-time
+// selection below (e.g. for a monomorphized build targeting one known host).
+#[cfg(all(
+    any(target_arch = "x86_64", target_arch = "x86"),
+    not(feature = "force_static_dispatch"),
+    not(all(
+        target_feature = "aes",
+        target_feature = "sse2",
+        target_feature = "ssse3"
+    )),
+))]
+mod dispatch;
+
+#[cfg(all(
     any(target_arch = "x86_64", target_arch = "x86"),
+    not(feature = "force_static_dispatch"),
+    not(all(
+        target_feature = "aes",
+        target_feature = "sse2",
+        target_feature = "ssse3"
+    )),
+))]
+use crate::dispatch as aes;
+
+#[cfg(not(any(
+    all(
+        any(target_arch = "x86_64", target_arch = "x86"),
+        not(feature = "force_static_dispatch"),
+        not(all(
+            target_feature = "aes",
+            target_feature = "sse2",
+            target_feature = "ssse3"
+        )),
+    ),
+    all(
+        target_feature = "aes",
+        target_feature = "sse2",
+        target_feature = "ssse3",
+        any(target_arch = "x86_64", target_arch = "x86"),
+    ),
+    all(target_arch = "aarch64", target_feature = "aes"),
 )))]
 use soft as aes;
 
@@ -73,6 +153,9 @@ use soft as aes;
 ))]
 use aesni as aes;
 
+#[cfg(all(target_arch = "aarch64", target_feature = "aes"))]
+use crate::armv8 as aes;
+
 pub use crate::aes::{Aes128Ctr, Aes192Ctr, Aes256Ctr};
 
 #[test]
@@ -151,6 +234,241 @@ fn compare_to_openssl_near_128bit_be() {
     compare_scenario(&[0; 16], &[0; 4 * 16], &nonce, &expected);
 }
 
+#[test]
+fn ctr32be_wraps_only_the_low_32_bits() {
+    use crate::Ctr32BE;
+    use cipher::{NewStreamCipher, SyncStreamCipher};
+
+    let key = [0u8; 16];
+    let mut nonce = [0x11u8; 16];
+    nonce[12..].copy_from_slice(&u32::MAX.to_be_bytes());
+
+    let mut at_wrap = Aes128Ctr::<Ctr32BE>::new_var(&key, &nonce).unwrap();
+    let mut first = [0u8; 16];
+    let mut second = [0u8; 16];
+    at_wrap.apply_keystream(&mut first);
+    at_wrap.apply_keystream(&mut second);
+
+    // wrapping the 32-bit counter must reset only the low 4 bytes, leaving
+    // the high 96 bits (the fixed nonce) untouched
+    let mut wrapped_nonce = nonce;
+    wrapped_nonce[12..].copy_from_slice(&0u32.to_be_bytes());
+    let mut from_zero = Aes128Ctr::<Ctr32BE>::new_var(&key, &wrapped_nonce).unwrap();
+    let mut expected_second = [0u8; 16];
+    from_zero.apply_keystream(&mut expected_second);
+
+    assert_eq!(second, expected_second);
+    assert_ne!(first, second);
+}
+
+#[test]
+fn ctr32be_seek_stays_within_the_counter_field() {
+    use crate::Ctr32BE;
+    use cipher::{NewStreamCipher, SyncStreamCipher, SyncStreamCipherSeek};
+
+    let key = [0u8; 16];
+    let nonce = [0x22u8; 16];
+
+    let mut cipher = Aes128Ctr::<Ctr32BE>::new_var(&key, &nonce).unwrap();
+    let mut skipped = [0u8; 16 * 3];
+    cipher.apply_keystream(&mut skipped);
+
+    let mut seeked = Aes128Ctr::<Ctr32BE>::new_var(&key, &nonce).unwrap();
+    seeked.seek(2 * 16);
+    let mut block = [0u8; 16];
+    seeked.apply_keystream(&mut block);
+
+    assert_eq!(block, skipped[2 * 16..]);
+}
+
+#[test]
+fn ctr32be_current_pos_does_not_overflow_at_wrap() {
+    use crate::Ctr32BE;
+    use cipher::{NewStreamCipher, SyncStreamCipherSeek};
+
+    let key = [0u8; 16];
+    let nonce = [0x55u8; 16];
+
+    let mut cipher = Aes128Ctr::<Ctr32BE>::new_var(&key, &nonce).unwrap();
+
+    // seek into the middle of the very last block before the 32-bit counter
+    // field wraps back to the nonce's starting value; `current_pos` must
+    // recover this position rather than panicking (debug) or returning
+    // garbage (release) when the wrapped field reads back as block index 0
+    let last_block_before_wrap = u64::from(u32::MAX) * 16;
+    cipher.seek(last_block_before_wrap + 4);
+
+    assert_eq!(cipher.current_pos(), last_block_before_wrap + 4);
+}
+
+#[test]
+fn ctr64be_wraps_only_the_low_64_bits() {
+    use crate::Ctr64BE;
+    use cipher::{NewStreamCipher, SyncStreamCipher};
+
+    let key = [0u8; 16];
+    let mut nonce = [0x11u8; 16];
+    nonce[8..].copy_from_slice(&u64::MAX.to_be_bytes());
+
+    let mut at_wrap = Aes128Ctr::<Ctr64BE>::new_var(&key, &nonce).unwrap();
+    let mut first = [0u8; 16];
+    let mut second = [0u8; 16];
+    at_wrap.apply_keystream(&mut first);
+    at_wrap.apply_keystream(&mut second);
+
+    let mut wrapped_nonce = nonce;
+    wrapped_nonce[8..].copy_from_slice(&0u64.to_be_bytes());
+    let mut from_zero = Aes128Ctr::<Ctr64BE>::new_var(&key, &wrapped_nonce).unwrap();
+    let mut expected_second = [0u8; 16];
+    from_zero.apply_keystream(&mut expected_second);
+
+    assert_eq!(second, expected_second);
+    assert_ne!(first, second);
+}
+
+#[test]
+fn ctr64be_seek_stays_within_the_counter_field() {
+    use crate::Ctr64BE;
+    use cipher::{NewStreamCipher, SyncStreamCipher, SyncStreamCipherSeek};
+
+    let key = [0u8; 16];
+    let nonce = [0x22u8; 16];
+
+    let mut cipher = Aes128Ctr::<Ctr64BE>::new_var(&key, &nonce).unwrap();
+    let mut skipped = [0u8; 16 * 3];
+    cipher.apply_keystream(&mut skipped);
+
+    let mut seeked = Aes128Ctr::<Ctr64BE>::new_var(&key, &nonce).unwrap();
+    seeked.seek(2 * 16);
+    let mut block = [0u8; 16];
+    seeked.apply_keystream(&mut block);
+
+    assert_eq!(block, skipped[2 * 16..]);
+}
+
+#[test]
+fn ctr32le_wraps_only_the_low_32_bits() {
+    use crate::Ctr32LE;
+    use cipher::{NewStreamCipher, SyncStreamCipher};
+
+    let key = [0u8; 16];
+    let mut nonce = [0x11u8; 16];
+    nonce[12..].copy_from_slice(&u32::MAX.to_le_bytes());
+
+    let mut at_wrap = Aes128Ctr::<Ctr32LE>::new_var(&key, &nonce).unwrap();
+    let mut first = [0u8; 16];
+    let mut second = [0u8; 16];
+    at_wrap.apply_keystream(&mut first);
+    at_wrap.apply_keystream(&mut second);
+
+    let mut wrapped_nonce = nonce;
+    wrapped_nonce[12..].copy_from_slice(&0u32.to_le_bytes());
+    let mut from_zero = Aes128Ctr::<Ctr32LE>::new_var(&key, &wrapped_nonce).unwrap();
+    let mut expected_second = [0u8; 16];
+    from_zero.apply_keystream(&mut expected_second);
+
+    assert_eq!(second, expected_second);
+    assert_ne!(first, second);
+
+    // a byte-order mistake (treating the field as BE) would still wrap at
+    // the same point, but the first increment after the wrap would bump the
+    // other end of the field and produce a different keystream block
+    let mut next_after_wrap = [0u8; 16];
+    from_zero.apply_keystream(&mut next_after_wrap);
+    let mut as_be = Aes128Ctr::<Ctr32BE>::new_var(&key, &wrapped_nonce).unwrap();
+    let mut as_be_blocks = [0u8; 32];
+    as_be.apply_keystream(&mut as_be_blocks);
+    assert_ne!(next_after_wrap, as_be_blocks[16..]);
+}
+
+#[test]
+fn ctr32le_seek_stays_within_the_counter_field() {
+    use crate::Ctr32LE;
+    use cipher::{NewStreamCipher, SyncStreamCipher, SyncStreamCipherSeek};
+
+    let key = [0u8; 16];
+    let nonce = [0x22u8; 16];
+
+    let mut cipher = Aes128Ctr::<Ctr32LE>::new_var(&key, &nonce).unwrap();
+    let mut skipped = [0u8; 16 * 3];
+    cipher.apply_keystream(&mut skipped);
+
+    let mut seeked = Aes128Ctr::<Ctr32LE>::new_var(&key, &nonce).unwrap();
+    seeked.seek(2 * 16);
+    let mut block = [0u8; 16];
+    seeked.apply_keystream(&mut block);
+
+    assert_eq!(block, skipped[2 * 16..]);
+}
+
+#[test]
+fn ctr64le_wraps_only_the_low_64_bits() {
+    use crate::Ctr64LE;
+    use cipher::{NewStreamCipher, SyncStreamCipher};
+
+    let key = [0u8; 16];
+    let mut nonce = [0x11u8; 16];
+    nonce[8..].copy_from_slice(&u64::MAX.to_le_bytes());
+
+    let mut at_wrap = Aes128Ctr::<Ctr64LE>::new_var(&key, &nonce).unwrap();
+    let mut first = [0u8; 16];
+    let mut second = [0u8; 16];
+    at_wrap.apply_keystream(&mut first);
+    at_wrap.apply_keystream(&mut second);
+
+    let mut wrapped_nonce = nonce;
+    wrapped_nonce[8..].copy_from_slice(&0u64.to_le_bytes());
+    let mut from_zero = Aes128Ctr::<Ctr64LE>::new_var(&key, &wrapped_nonce).unwrap();
+    let mut expected_second = [0u8; 16];
+    from_zero.apply_keystream(&mut expected_second);
+
+    assert_eq!(second, expected_second);
+    assert_ne!(first, second);
+}
+
+#[test]
+fn ctr64le_seek_stays_within_the_counter_field() {
+    use crate::Ctr64LE;
+    use cipher::{NewStreamCipher, SyncStreamCipher, SyncStreamCipherSeek};
+
+    let key = [0u8; 16];
+    let nonce = [0x22u8; 16];
+
+    let mut cipher = Aes128Ctr::<Ctr64LE>::new_var(&key, &nonce).unwrap();
+    let mut skipped = [0u8; 16 * 3];
+    cipher.apply_keystream(&mut skipped);
+
+    let mut seeked = Aes128Ctr::<Ctr64LE>::new_var(&key, &nonce).unwrap();
+    seeked.seek(2 * 16);
+    let mut block = [0u8; 16];
+    seeked.apply_keystream(&mut block);
+
+    assert_eq!(block, skipped[2 * 16..]);
+}
+
+#[test]
+fn current_pos_matches_exact_block_multiples() {
+    use cipher::{NewStreamCipher, SyncStreamCipher, SyncStreamCipherSeek};
+
+    let key = [0u8; 16];
+    let nonce = [0x33u8; 16];
+
+    let mut cipher = Aes128Ctr::new_var(&key, &nonce).unwrap();
+    assert_eq!(cipher.current_pos(), 0);
+
+    let mut block = [0u8; 16];
+    cipher.apply_keystream(&mut block);
+    assert_eq!(cipher.current_pos(), 16);
+
+    let mut second_block = [0u8; 16];
+    cipher.apply_keystream(&mut second_block);
+    assert_eq!(cipher.current_pos(), 32);
+
+    // seeking back to an exact block boundary must round-trip too
+    cipher.seek(16);
+    assert_eq!(cipher.current_pos(), 16);
+}
+
 /// Run aes-ctr against openssl generated next four blocks from the nonce.
 #[cfg(test)]
 fn compare_scenario(key: &[u8], data: &[u8], nonce: &[u8], expected: &[u8]) {
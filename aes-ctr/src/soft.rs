@@ -0,0 +1,156 @@
+//! Portable AES-CTR backend with no hardware acceleration, used whenever
+//! neither AES-NI nor the ARMv8 Cryptography Extensions are available: the
+//! default `cargo build`/`cargo test` configuration, any non-x86/aarch64
+//! target, and (via [`crate::dispatch`]) the runtime fallback path on x86
+//! hosts that lack `aes`/`sse2`/`ssse3`.
+//!
+//! The block cipher itself is [`crate::key_schedule::encrypt_block_portable`],
+//! shared with [`crate::aes_siv`]'s CMAC; this module only adds the CTR
+//! keystream bookkeeping around it.
+
+use crate::cipher::{
+    errors::InvalidKeyNonceLength,
+    generic_array::{
+        typenum::{U16, U24, U32},
+        GenericArray,
+    },
+    stream::{NewStreamCipher, SyncStreamCipher, SyncStreamCipherSeek},
+};
+use crate::flavors::{Ctr128BE, CtrFlavor};
+use crate::key_schedule::{encrypt_block_portable, expand_key};
+use core::marker::PhantomData;
+
+const BLOCK_SIZE: usize = 16;
+
+macro_rules! impl_soft_ctr {
+    ($name:ident, $key_size:ty, $key_words:expr, $rounds:expr, $doc:expr) => {
+        #[doc = $doc]
+        ///
+        /// Generic over the counter-block policy `F` (see [`crate::flavors`]);
+        /// defaults to [`Ctr128BE`], this crate's historical full-width
+        /// big-endian counter.
+        pub struct $name<F = Ctr128BE> {
+            round_keys: [[u8; BLOCK_SIZE]; $rounds + 1],
+            nonce: [u8; BLOCK_SIZE],
+            counter: [u8; BLOCK_SIZE],
+            block: [u8; BLOCK_SIZE],
+            pos: u8,
+            _flavor: PhantomData<F>,
+        }
+
+        impl<F: CtrFlavor> $name<F> {
+            fn keystream_block(&mut self) {
+                self.block = self.counter;
+                encrypt_block_portable(&self.round_keys, $rounds, &mut self.block);
+                F::increment(&mut self.counter);
+            }
+        }
+
+        impl<F: CtrFlavor> NewStreamCipher for $name<F> {
+            type KeySize = $key_size;
+            type NonceSize = U16;
+
+            fn new(
+                key: &GenericArray<u8, Self::KeySize>,
+                nonce: &GenericArray<u8, Self::NonceSize>,
+            ) -> Self {
+                let expanded = expand_key(key.as_slice(), $key_words, $rounds);
+                let mut round_keys = [[0u8; BLOCK_SIZE]; $rounds + 1];
+                round_keys.copy_from_slice(&expanded[..$rounds + 1]);
+                let mut nonce_block = [0u8; BLOCK_SIZE];
+                nonce_block.copy_from_slice(nonce.as_slice());
+                let counter = F::counter_at(&nonce_block, 0);
+                Self {
+                    round_keys,
+                    nonce: nonce_block,
+                    counter,
+                    block: [0u8; BLOCK_SIZE],
+                    pos: BLOCK_SIZE as u8,
+                    _flavor: PhantomData,
+                }
+            }
+
+            fn new_var(key: &[u8], nonce: &[u8]) -> Result<Self, InvalidKeyNonceLength> {
+                if key.len() != Self::key_size() || nonce.len() != Self::nonce_size() {
+                    return Err(InvalidKeyNonceLength);
+                }
+                Ok(Self::new(
+                    GenericArray::from_slice(key),
+                    GenericArray::from_slice(nonce),
+                ))
+            }
+        }
+
+        impl<F: CtrFlavor> SyncStreamCipher for $name<F> {
+            fn apply_keystream(&mut self, mut data: &mut [u8]) {
+                while !data.is_empty() {
+                    if self.pos as usize == BLOCK_SIZE {
+                        self.keystream_block();
+                        self.pos = 0;
+                    }
+                    let n = core::cmp::min(data.len(), BLOCK_SIZE - self.pos as usize);
+                    for (b, k) in data[..n]
+                        .iter_mut()
+                        .zip(self.block[self.pos as usize..].iter())
+                    {
+                        *b ^= k;
+                    }
+                    self.pos += n as u8;
+                    data = &mut data[n..];
+                }
+            }
+        }
+
+        impl<F: CtrFlavor> SyncStreamCipherSeek for $name<F> {
+            fn current_pos(&self) -> u64 {
+                let block_index = F::block_index(&self.nonce, &self.counter);
+                if self.pos as usize == BLOCK_SIZE {
+                    block_index as u64 * BLOCK_SIZE as u64
+                } else {
+                    // Not a plain `block_index - 1`: once a narrow counter
+                    // field has wrapped back to the nonce's value,
+                    // `block_index` is `0`, and subtracting one as a `u128`
+                    // would overflow the multiply below.
+                    F::previous_block_index(block_index) as u64 * BLOCK_SIZE as u64
+                        + self.pos as u64
+                }
+            }
+
+            fn seek(&mut self, pos: u64) {
+                let block = pos / BLOCK_SIZE as u64;
+                self.counter = F::counter_at(&self.nonce, block as u128);
+                self.keystream_block();
+                self.pos = (pos % BLOCK_SIZE as u64) as u8;
+            }
+        }
+
+        impl<F> core::fmt::Debug for $name<F> {
+            fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+                // Deliberately don't print round keys or counter/nonce state.
+                f.debug_struct(stringify!($name)).finish_non_exhaustive()
+            }
+        }
+    };
+}
+
+impl_soft_ctr!(
+    Aes128Ctr,
+    U16,
+    4,
+    10,
+    "AES-128 in CTR mode, pure software (no hardware acceleration)"
+);
+impl_soft_ctr!(
+    Aes192Ctr,
+    U24,
+    6,
+    12,
+    "AES-192 in CTR mode, pure software (no hardware acceleration)"
+);
+impl_soft_ctr!(
+    Aes256Ctr,
+    U32,
+    8,
+    14,
+    "AES-256 in CTR mode, pure software (no hardware acceleration)"
+);